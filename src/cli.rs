@@ -0,0 +1,99 @@
+//! Command-line argument parsing.
+//!
+//! Everything used to be baked into `main` and read only from environment
+//! variables; this gives the tool real flags so it can be driven
+//! interactively or from CI without editing source or juggling a `.env`.
+
+use chrono::NaiveDate;
+use clap::Parser;
+
+fn parse_date_expire(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| format!("invalid date {value:?}, expected YYYY-MM-DD: {e}"))
+}
+
+/// Automates login and Adobe reservation renewal on the KMUTNB software portal.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    /// Username to log in with; overrides KMUTNB_USERNAME.
+    #[arg(long)]
+    pub username: Option<String>,
+
+    /// Password to log in with; overrides KMUTNB_PASSWORD.
+    #[arg(long)]
+    pub password: Option<String>,
+
+    /// Explicit date_expire override (YYYY-MM-01); takes precedence over --lease.
+    #[arg(long, alias = "expire", value_parser = parse_date_expire)]
+    pub date_expire: Option<NaiveDate>,
+
+    /// Lease length to request, e.g. "3mo". Ignored when --date-expire is set.
+    #[arg(long)]
+    pub lease: Option<String>,
+
+    /// Perform login but print the reservation payload instead of submitting it.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Log in and report the current reservation state without changing anything.
+    #[arg(long)]
+    pub status: bool,
+
+    /// Run forever, renewing the lease shortly before it expires.
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// Report the portal's TLS certificate validity and exit.
+    #[arg(long)]
+    pub check: bool,
+
+    /// Disable TLS verification instead of certificate pinning (dangerous).
+    #[arg(long)]
+    pub insecure: bool,
+
+    /// Log (rather than fail on) network errors that survive the retry policy.
+    #[arg(long)]
+    pub ignore_errors: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_malformed_date_expire() {
+        let result = Cli::try_parse_from(["auto-adobe-rs", "--date-expire", "not-a-date"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn accepts_valid_date_expire() {
+        let cli = Cli::try_parse_from(["auto-adobe-rs", "--date-expire", "2025-06-01"]).unwrap();
+        assert_eq!(
+            cli.date_expire,
+            Some(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn expire_alias_matches_date_expire() {
+        let cli = Cli::try_parse_from(["auto-adobe-rs", "--expire", "2025-06-01"]).unwrap();
+        assert_eq!(
+            cli.date_expire,
+            Some(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn defaults_are_all_off() {
+        let cli = Cli::try_parse_from(["auto-adobe-rs"]).unwrap();
+        assert!(!cli.dry_run);
+        assert!(!cli.status);
+        assert!(!cli.daemon);
+        assert!(!cli.check);
+        assert!(!cli.insecure);
+        assert!(cli.date_expire.is_none());
+        assert!(cli.lease.is_none());
+    }
+}
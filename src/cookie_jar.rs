@@ -0,0 +1,245 @@
+//! Netscape-format cookie jar persistence.
+//!
+//! Lets the tool remember a valid login session across process invocations by
+//! serializing the `reqwest` cookie store to a tab-separated file in the same
+//! layout `curl`/`wget` use (`domain`, `include_subdomains`, `path`,
+//! `https_only`, `expires`, `name`, `value`). This lets a second run of the
+//! script skip the login POST when the previous session cookie is still live.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single cookie record, as read from (or destined for) a Netscape cookie
+/// jar file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub domain: String,
+    pub include_subdomains: bool,
+    pub path: String,
+    pub https_only: bool,
+    /// Unix-seconds expiry. `0` means a session cookie that never expires.
+    pub expires: i64,
+    pub name: String,
+    pub value: String,
+}
+
+impl Cookie {
+    /// Whether this cookie has passed its `expires` instant. A `0` expiry
+    /// (session cookie) is treated as never expired.
+    pub fn is_expired(&self) -> bool {
+        if self.expires == 0 {
+            return false;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        now >= self.expires
+    }
+
+    /// Whether this cookie should be sent for the given domain/scheme,
+    /// rejecting `https_only` cookies over a plain-HTTP URL.
+    pub fn matches_url(&self, domain: &str, is_https: bool) -> bool {
+        if self.https_only && !is_https {
+            return false;
+        }
+        if self.include_subdomains {
+            domain == self.domain || domain.ends_with(&format!(".{}", self.domain))
+        } else {
+            domain == self.domain
+        }
+    }
+}
+
+/// Load a Netscape-format cookie jar from `path`, dropping expired entries.
+///
+/// Returns an empty `Vec` (not an error) if the file does not exist yet, so
+/// callers can treat "no jar" the same as "no live session".
+pub fn load(path: &Path) -> std::io::Result<Vec<Cookie>> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut cookies = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = trimmed.split('\t').collect();
+        if fields.len() != 7 {
+            continue;
+        }
+        let cookie = Cookie {
+            domain: fields[0].to_string(),
+            include_subdomains: fields[1].eq_ignore_ascii_case("TRUE"),
+            path: fields[2].to_string(),
+            https_only: fields[3].eq_ignore_ascii_case("TRUE"),
+            expires: fields[4].parse().unwrap_or(0),
+            name: fields[5].to_string(),
+            value: fields[6].to_string(),
+        };
+        if !cookie.is_expired() {
+            cookies.push(cookie);
+        }
+    }
+    Ok(cookies)
+}
+
+/// Write `cookies` back out to `path` in Netscape format, overwriting any
+/// existing file.
+pub fn save(path: &Path, cookies: &[Cookie]) -> std::io::Result<()> {
+    let mut out = String::new();
+    out.push_str("# Netscape HTTP Cookie File\n");
+    for cookie in cookies {
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            cookie.domain,
+            if cookie.include_subdomains { "TRUE" } else { "FALSE" },
+            cookie.path,
+            if cookie.https_only { "TRUE" } else { "FALSE" },
+            cookie.expires,
+            cookie.name,
+            cookie.value,
+        ));
+    }
+    let mut file = fs::File::create(path)?;
+    file.write_all(out.as_bytes())
+}
+
+/// Whether `cookies` contains a live (non-expired) session cookie for
+/// `domain`, meaning the login step can be skipped.
+pub fn has_live_session(cookies: &[Cookie], domain: &str) -> bool {
+    cookies
+        .iter()
+        .any(|c| c.domain == domain && !c.is_expired())
+}
+
+/// Parse a single `Set-Cookie` header value into a [`Cookie`], falling back
+/// to `default_domain` and a `/` path for attributes the server omitted.
+///
+/// Only the attributes this tool cares about are recognised (`Domain`,
+/// `Path`, `Secure`, `Max-Age`); anything else (`SameSite`, `HttpOnly`, ...)
+/// is ignored.
+pub fn parse_set_cookie(header_value: &str, default_domain: &str) -> Option<Cookie> {
+    let mut parts = header_value.split(';');
+    let name_value = parts.next()?.trim();
+    let (name, value) = name_value.split_once('=')?;
+
+    let mut domain = default_domain.to_string();
+    let mut path = "/".to_string();
+    let mut https_only = false;
+    let mut expires: i64 = 0;
+
+    for attr in parts {
+        let attr = attr.trim();
+        if let Some(v) = attr.strip_prefix("Domain=").or_else(|| attr.strip_prefix("domain=")) {
+            domain = v.trim_start_matches('.').to_string();
+        } else if let Some(v) = attr.strip_prefix("Path=").or_else(|| attr.strip_prefix("path=")) {
+            path = v.to_string();
+        } else if attr.eq_ignore_ascii_case("Secure") {
+            https_only = true;
+        } else if let Some(v) = attr.strip_prefix("Max-Age=").or_else(|| attr.strip_prefix("max-age=")) {
+            if let Ok(secs) = v.parse::<i64>() {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or(0);
+                expires = now + secs;
+            }
+        }
+    }
+
+    Some(Cookie {
+        domain,
+        include_subdomains: false,
+        path,
+        https_only,
+        expires,
+        name: name.to_string(),
+        value: value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie(expires: i64) -> Cookie {
+        Cookie {
+            domain: "software.kmutnb.ac.th".to_string(),
+            include_subdomains: false,
+            path: "/".to_string(),
+            https_only: true,
+            expires,
+            name: "PHPSESSID".to_string(),
+            value: "abc123".to_string(),
+        }
+    }
+
+    #[test]
+    fn session_cookie_never_expires() {
+        assert!(!cookie(0).is_expired());
+    }
+
+    #[test]
+    fn past_expiry_is_expired() {
+        assert!(cookie(1).is_expired());
+    }
+
+    #[test]
+    fn far_future_expiry_is_not_expired() {
+        assert!(!cookie(4_102_444_800).is_expired());
+    }
+
+    #[test]
+    fn https_only_cookie_rejected_over_http() {
+        let c = cookie(0);
+        assert!(c.matches_url("software.kmutnb.ac.th", true));
+        assert!(!c.matches_url("software.kmutnb.ac.th", false));
+    }
+
+    #[test]
+    fn has_live_session_detects_domain() {
+        let cookies = vec![cookie(0)];
+        assert!(has_live_session(&cookies, "software.kmutnb.ac.th"));
+        assert!(!has_live_session(&cookies, "other.example.com"));
+    }
+
+    #[test]
+    fn parses_set_cookie_header() {
+        let c = parse_set_cookie(
+            "PHPSESSID=abc123; path=/; Secure; Max-Age=3600",
+            "software.kmutnb.ac.th",
+        )
+        .unwrap();
+        assert_eq!(c.name, "PHPSESSID");
+        assert_eq!(c.value, "abc123");
+        assert_eq!(c.domain, "software.kmutnb.ac.th");
+        assert!(c.https_only);
+        assert!(c.expires > 0);
+    }
+
+    #[test]
+    fn parses_set_cookie_without_max_age_as_session() {
+        let c = parse_set_cookie("PHPSESSID=abc123; path=/", "software.kmutnb.ac.th").unwrap();
+        assert_eq!(c.expires, 0);
+        assert!(!c.is_expired());
+    }
+
+    #[test]
+    fn save_then_load_round_trips() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("cookie_jar_test_{:?}.txt", std::thread::current().id()));
+        let cookies = vec![cookie(0)];
+        save(&path, &cookies).unwrap();
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded, cookies);
+        let _ = fs::remove_file(&path);
+    }
+}
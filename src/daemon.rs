@@ -0,0 +1,106 @@
+//! `--daemon` mode: keep renewing the Adobe lease forever instead of
+//! exiting after one reservation.
+//!
+//! Each cycle re-reads credentials and the cookie jar (via
+//! [`crate::run_once`]), so a jar refreshed by a previous cycle or a
+//! `--password` flag is picked up. Credentials taken from `.env` are loaded
+//! with `dotenv()`, which does not overwrite variables already set, so an
+//! edit to `.env` after the first cycle is not seen without a restart.
+//! Every cycle also forces a fresh login (`force_login = true`), rather than
+//! reusing a still-"live" session cookie, so a rotated password is actually
+//! exercised instead of silently never being checked again.
+
+use chrono::NaiveDate;
+use std::thread;
+use std::time::Duration;
+
+/// How long before the lease's `date_expire` boundary to wake up and renew.
+const RENEW_LEAD_TIME: Duration = Duration::from_secs(60 * 60);
+
+/// Fallback sleep after a failed cycle, so a transient network error doesn't
+/// spin the loop.
+const RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Extra sleep tacked onto a wake-up that lands past `date_expire`'s
+/// boundary, so the next cycle's `NaiveDate` is safely on the other side of
+/// midnight rather than racing it.
+const RENEW_BOUNDARY_BUFFER: Duration = Duration::from_secs(5 * 60);
+
+/// Run the renewal loop until the process is killed.
+pub fn run(cli: &crate::cli::Cli) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        match crate::run_once(cli, true) {
+            Ok(date_expire) => {
+                let sleep_for = duration_until_renewal(&date_expire);
+                tracing::info!(
+                    %date_expire,
+                    wake_in = format_wake_time(sleep_for),
+                    "reservation renewed; sleeping until shortly before expiry"
+                );
+                thread::sleep(sleep_for);
+            }
+            Err(e) => {
+                tracing::error!(error = %e, retry_in = ?RETRY_DELAY, "renewal cycle failed");
+                thread::sleep(RETRY_DELAY);
+            }
+        }
+    }
+}
+
+/// Compute how long to sleep before the next renewal attempt, waking up
+/// `RENEW_LEAD_TIME` before `date_expire` (or after `RETRY_DELAY` if that
+/// boundary is already in the past or the date can't be parsed).
+///
+/// `date_expire` is a `YYYY-MM-01` boundary with no time component, so a
+/// lease recomputed anywhere inside that last `RENEW_LEAD_TIME` window still
+/// resolves to the *same* `date_expire`: waking up again after only
+/// `RETRY_DELAY` would just re-submit the same reservation. So once we're
+/// inside that window, sleep past the boundary itself instead, so the next
+/// cycle's recomputed `date_expire` has actually advanced.
+fn duration_until_renewal(date_expire: &str) -> Duration {
+    let expire_date = match NaiveDate::parse_from_str(date_expire, "%Y-%m-%d") {
+        Ok(d) => d,
+        Err(_) => return RETRY_DELAY,
+    };
+    let now = chrono::Local::now().naive_local();
+    let expire_at_midnight = expire_date.and_hms_opt(0, 0, 0).unwrap();
+    let until_expiry = expire_at_midnight.signed_duration_since(now);
+
+    match until_expiry.to_std() {
+        Ok(d) if d > RENEW_LEAD_TIME => d - RENEW_LEAD_TIME,
+        Ok(d) => d + RENEW_BOUNDARY_BUFFER,
+        Err(_) => RETRY_DELAY,
+    }
+}
+
+fn format_wake_time(d: Duration) -> String {
+    let hours = d.as_secs() / 3600;
+    let minutes = (d.as_secs() % 3600) / 60;
+    format!("{hours}h{minutes}m")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_future_expiry_sleeps_until_lead_time() {
+        let far_future = (chrono::Local::now().date_naive() + chrono::Duration::days(30))
+            .format("%Y-%m-%d")
+            .to_string();
+        let d = duration_until_renewal(&far_future);
+        assert!(d > RENEW_LEAD_TIME);
+    }
+
+    #[test]
+    fn past_expiry_falls_back_to_retry_delay() {
+        let d = duration_until_renewal("2000-01-01");
+        assert_eq!(d, RETRY_DELAY);
+    }
+
+    #[test]
+    fn malformed_date_falls_back_to_retry_delay() {
+        let d = duration_until_renewal("not-a-date");
+        assert_eq!(d, RETRY_DELAY);
+    }
+}
@@ -5,35 +5,125 @@
 //!   variables.
 //! - Submit a request to the portal's Adobe reservation endpoint to extend/grant access
 //!   (the script computes a target `date_expire` value automatically).
+//! - Persist the session cookie to a Netscape-format cookie jar file so repeated runs
+//!   can skip the login round-trip entirely (see [`cookie_jar`]).
+//! - Optionally run forever as a renewal daemon via `--daemon` (see [`daemon`]), so the
+//!   lease is re-submitted shortly before it expires without an external cron job.
+//! - Pin the portal's TLS certificate and pre-check its expiry instead of disabling
+//!   verification outright (see [`tls`]); `--check` reports cert health without logging in.
+//! - Parse real CLI flags (see [`cli`]) for credential overrides, `--dry-run`, and
+//!   `--status`, instead of requiring a `.env` file for every option.
+//! - Retry transient HTTP failures with backoff (see [`retry`]) and emit structured
+//!   logs controllable via `RUST_LOG`, instead of aborting on the first hiccup.
+
+mod cli;
+mod cookie_jar;
+mod daemon;
+mod retry;
+mod tls;
 
 use chrono::{Datelike, Local};
+use clap::Parser;
+use cli::Cli;
 use dotenv::dotenv;
-use reqwest::blocking::Client;
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, ORIGIN, REFERER, USER_AGENT};
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, ORIGIN, REFERER, SET_COOKIE, USER_AGENT,
+};
+use retry::RetryConfig;
 use std::env;
+use std::path::Path;
 use std::time::Duration;
 
 // LOGIN/ENDPOINT URL CONSTANTS
 const LOGIN_URL: &str = "https://software.kmutnb.ac.th/login/";
 const ADOBE_PROCESS_URL: &str = "https://software.kmutnb.ac.th/adobe-reserve/processa.php";
 const ADOBE_URL: &str = "https://software.kmutnb.ac.th:443/adobe-reserve/add2.php";
+const LOGIN_DOMAIN: &str = "software.kmutnb.ac.th";
 
 const USER_AGENT_VALUE: &str =
     "Mozilla/5.0 (X11; Linux x86_64; rv:146.0) Gecko/20100101 Firefox/146.0";
 const ORIGIN_VALUE: &str = "https://software.kmutnb.ac.th";
 
-/// Compute the first day of the next month using the original script's logic.
-///
-/// The original one-liner used a special case where if month == 1, the year was
-/// decremented by 1 and the month set to 12; this function preserves that behavior.
-/// Returns a string formatted as 'YYYY-MM-01'.
-fn make_date_expire(year: i32, month: u32) -> String {
-    let (new_year, new_month) = if month == 1 {
-        (year - 1, 12)
-    } else {
-        (year, month + 1)
-    };
-    format!("{:04}-{:02}-01", new_year, new_month)
+/// Path to the persisted Netscape-format cookie jar, overridable via
+/// `COOKIE_JAR_PATH` for tests or multi-account setups.
+fn cookie_jar_path() -> String {
+    env::var("COOKIE_JAR_PATH").unwrap_or_else(|_| "cookies.txt".to_string())
+}
+
+/// Path to a PEM bundle to pin as the trusted root/leaf certificate,
+/// overriding the system trust store. `None` means "trust the system
+/// store as usual" (pinning is opt-in, not required).
+fn tls_pin_bundle_path() -> Option<String> {
+    env::var("TLS_PIN_BUNDLE_PATH").ok()
+}
+
+/// Minimum number of days of certificate validity before the expiry
+/// pre-check warns (or refuses), overridable via `TLS_MIN_DAYS`.
+fn tls_min_days() -> i64 {
+    env::var("TLS_MIN_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(tls::DEFAULT_MIN_DAYS_REMAINING)
+}
+
+/// Whether a near-expiry certificate should abort the run rather than just
+/// print a warning, controlled via `TLS_REFUSE_ON_EXPIRY=1`.
+fn tls_refuse_on_expiry() -> bool {
+    env::var("TLS_REFUSE_ON_EXPIRY")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Resolve the lease to request from parsed CLI flags: an explicit
+/// `--date-expire` wins, otherwise `--lease <N>mo` is parsed, otherwise fall
+/// back to a one-month lease (the original script's behavior).
+fn lease_spec_from_cli(cli: &Cli) -> Result<LeaseSpec, Box<dyn std::error::Error>> {
+    if let Some(date) = cli.date_expire {
+        return Ok(LeaseSpec::ExplicitDate(date));
+    }
+
+    if let Some(value) = &cli.lease {
+        let months: u32 = value
+            .strip_suffix("mo")
+            .ok_or_else(|| format!("invalid --lease value {value:?}, expected e.g. '3mo'"))?
+            .parse()
+            .map_err(|e| format!("invalid --lease value {value:?}: {e}"))?;
+        return Ok(LeaseSpec::Months(months));
+    }
+
+    Ok(LeaseSpec::Months(1))
+}
+
+/// Requested lease length for the Adobe reservation.
+enum LeaseSpec {
+    /// Snap to the first day of the month `n` months out from "now".
+    Months(u32),
+    /// Use an explicit caller-supplied expiry date (its day-of-month is
+    /// ignored; the request is always for the first of that month).
+    ExplicitDate(chrono::NaiveDate),
+}
+
+/// Add `months_to_add` calendar months to `(year, month)`, carrying the year
+/// over as needed (e.g. `(2024, 12) + 1 => (2025, 1)`).
+fn add_months(year: i32, month: u32, months_to_add: u32) -> (i32, u32) {
+    let zero_based_total = (month - 1) + months_to_add;
+    let new_year = year + (zero_based_total / 12) as i32;
+    let new_month = zero_based_total % 12 + 1;
+    (new_year, new_month)
+}
+
+/// Compute the `date_expire` value to submit for the given lease, relative
+/// to the current date `today`. Like a token's "time < expiry" caveat: a
+/// `Months` lease gives `now + lease`, snapped to the first of that month;
+/// an `ExplicitDate` is snapped to the first of its own month.
+fn compute_date_expire(today: chrono::NaiveDate, lease: LeaseSpec) -> String {
+    match lease {
+        LeaseSpec::ExplicitDate(d) => format!("{:04}-{:02}-01", d.year(), d.month()),
+        LeaseSpec::Months(n) => {
+            let (new_year, new_month) = add_months(today.year(), today.month(), n);
+            format!("{:04}-{:02}-01", new_year, new_month)
+        }
+    }
 }
 
 /// Build headers for the login POST request.
@@ -66,45 +156,163 @@ fn build_adobe_headers() -> HeaderMap {
     headers
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// Perform the login POST, retrying transient network errors, and merge any
+/// `Set-Cookie`s the server returned into `stored_cookies` (persisting them
+/// to `jar_path`). Shared by the initial login and by the stale-session
+/// re-login fallback in `run_once`.
+fn perform_login(
+    client: &reqwest::blocking::Client,
+    retry_config: &RetryConfig,
+    username: &str,
+    password: &str,
+    jar_path: &Path,
+    stored_cookies: &mut Vec<cookie_jar::Cookie>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let login_data = [
+        ("myusername", username),
+        ("mypassword", password),
+        ("Submit", ""),
+    ];
+
+    let login_resp = retry::with_retry(retry_config, || {
+        client
+            .post(LOGIN_URL)
+            .headers(build_login_headers())
+            .form(&login_data)
+            .send()
+    })?;
+
+    tracing::info!(status = %login_resp.status(), "login response");
+    if !login_resp.status().is_success() {
+        return Err(format!("Login failed with status: {}", login_resp.status()).into());
+    }
+
+    for raw in login_resp.headers().get_all(SET_COOKIE) {
+        if let Ok(raw) = raw.to_str() {
+            if let Some(cookie) = cookie_jar::parse_set_cookie(raw, LOGIN_DOMAIN) {
+                stored_cookies.retain(|c| c.name != cookie.name || c.domain != cookie.domain);
+                stored_cookies.push(cookie);
+            }
+        }
+    }
+    cookie_jar::save(jar_path, stored_cookies)?;
+    Ok(())
+}
+
+/// Whether a response to a request made with a reused session cookie looks
+/// like the portal actually rejected it (as opposed to the reservation
+/// endpoint's normal success output).
+///
+/// A jar-reused `PHPSESSID` has no `Max-Age`, so [`cookie_jar::Cookie::is_expired`]
+/// can't tell a dead server-side session from a live one; this is the
+/// reactive fallback for that: a non-success status, or a body that looks
+/// like the login form, means the reused cookie no longer authenticates us.
+fn looks_unauthenticated(status: reqwest::StatusCode, body: &str) -> bool {
+    !status.is_success() || body.contains("name=\"mypassword\"")
+}
+
+/// Run a single login + Adobe-reservation cycle, re-reading credentials and
+/// the cookie jar so a jar written by a previous cycle is picked up. `.env`
+/// is loaded with `dotenv()`, which does not overwrite variables already
+/// set, so a `.env` edit made after an earlier cycle in the same process is
+/// not seen. Returns the `date_expire` value that
+/// was submitted, so callers (the daemon loop) can schedule the next run.
+///
+/// `force_login` skips the cookie-jar reuse check entirely and always logs
+/// in fresh; the daemon loop sets this so a rotated `KMUTNB_PASSWORD` is
+/// actually exercised every cycle, instead of being masked by a still-"live"
+/// (non-expiring) session cookie.
+pub(crate) fn run_once(cli: &Cli, force_login: bool) -> Result<String, Box<dyn std::error::Error>> {
     // Load environment variables from a .env file (if present)
     dotenv().ok();
 
-    // Credentials from environment
-    let username = env::var("KMUTNB_USERNAME").expect("KMUTNB_USERNAME must be set");
-    let password = env::var("KMUTNB_PASSWORD").expect("KMUTNB_PASSWORD must be set");
+    // Credentials: explicit --username/--password flags take precedence
+    // over the environment variables.
+    let username = cli
+        .username
+        .clone()
+        .or_else(|| env::var("KMUTNB_USERNAME").ok())
+        .expect("KMUTNB_USERNAME must be set (or pass --username)");
+    let password = cli
+        .password
+        .clone()
+        .or_else(|| env::var("KMUTNB_PASSWORD").ok())
+        .expect("KMUTNB_PASSWORD must be set (or pass --password)");
 
     // Get current date for date_expire calculation
     let today = Local::now().date_naive();
-    let date_expire = make_date_expire(today.year(), today.month());
-
-    // Build the HTTP client with cookie store and disabled SSL verification
-    // WARNING: Disabling SSL verification is insecure; only use for testing or
-    // when you understand the risks.
-    let client = Client::builder()
-        .cookie_store(true)
-        .danger_accept_invalid_certs(true)
-        .timeout(Duration::from_secs(10))
-        .build()?;
-
-    // Login form data
-    let login_data = [
-        ("myusername", username.as_str()),
-        ("mypassword", password.as_str()),
-        ("Submit", ""),
-    ];
+    let date_expire = compute_date_expire(today, lease_spec_from_cli(cli)?);
+    tracing::info!(%date_expire, "computed date_expire");
 
-    // Perform login
-    let login_resp = client
-        .post(LOGIN_URL)
-        .headers(build_login_headers())
-        .form(&login_data)
-        .send()?;
+    let retry_config = RetryConfig::from_env();
 
-    // Check if login succeeded
-    if !login_resp.status().is_success() {
-        eprintln!("Login request failed with status: {}", login_resp.status());
-        return Err(format!("Login failed with status: {}", login_resp.status()).into());
+    // Load any previously-persisted session cookies (expired ones are
+    // dropped on load) so a second run within the same session skips login.
+    let jar_path = cookie_jar_path();
+    let mut stored_cookies = cookie_jar::load(Path::new(&jar_path))?;
+    let has_live_session =
+        !force_login && cookie_jar::has_live_session(&stored_cookies, LOGIN_DOMAIN);
+
+    let insecure = cli.insecure;
+
+    // Pre-flight the portal's certificate expiry, the way an ops health
+    // check would, unless we've opted out of verification entirely.
+    if !insecure {
+        match tls::check_expiry(LOGIN_DOMAIN) {
+            Ok(status) => {
+                let min_days = tls_min_days();
+                if status.is_near_expiry(min_days) {
+                    let msg = format!(
+                        "portal certificate for {LOGIN_DOMAIN} expires {} ({} day(s) remaining, below the {min_days}-day threshold)",
+                        status.not_after, status.days_remaining
+                    );
+                    if tls_refuse_on_expiry() {
+                        return Err(msg.into());
+                    }
+                    eprintln!("warning: {msg}");
+                }
+            }
+            Err(e) => eprintln!("warning: TLS certificate expiry pre-check failed: {e}"),
+        }
+    }
+
+    // Build the HTTP client. By default it pins the portal's certificate
+    // (or trusts the system store, if no pin bundle is configured); the old
+    // `danger_accept_invalid_certs(true)` behavior is only reachable via the
+    // explicit `--insecure` flag.
+    let client = tls::build_client(
+        insecure,
+        tls_pin_bundle_path().as_deref(),
+        Duration::from_secs(10),
+    )?;
+
+    // Cookie header built from the stored cookies; it is forwarded
+    // explicitly on the reservation requests below when the stored session
+    // is reused, since reqwest's own jar does not hold it.
+    let stored_cookie_header = stored_cookies
+        .iter()
+        .filter(|c| c.matches_url(LOGIN_DOMAIN, true))
+        .map(|c| format!("{}={}", c.name, c.value))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    // Tracks whether this cycle has already performed a fresh login, so the
+    // stale-session fallback below (when a reused cookie turns out to be
+    // dead server-side) doesn't try to log in a second time.
+    let mut logged_in_this_cycle = false;
+
+    if has_live_session {
+        println!("Reusing existing session cookie; skipping login.");
+    } else {
+        perform_login(
+            &client,
+            &retry_config,
+            &username,
+            &password,
+            Path::new(&jar_path),
+            &mut stored_cookies,
+        )?;
+        logged_in_this_cycle = true;
     }
 
     // Adobe reservation form data
@@ -115,34 +323,182 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         ("Submit_get", ""),
     ];
 
-    // Submit Adobe reservation/add request
-    let adobe_resp = client
-        .post(ADOBE_URL)
-        .headers(build_adobe_headers())
-        .form(&adobe_data)
-        .send()?;
+    // Submit Adobe reservation/add request. When we reused a stored session
+    // cookie (rather than logging in this run), reqwest's own jar starts out
+    // empty, so the cookie has to be forwarded explicitly here.
+    let mut adobe_headers = build_adobe_headers();
+    if has_live_session && !stored_cookie_header.is_empty() {
+        adobe_headers.insert(
+            reqwest::header::COOKIE,
+            HeaderValue::from_str(&stored_cookie_header)?,
+        );
+    }
+
+    if cli.status {
+        // Query the current reservation state without mutating anything.
+        let status_resp = retry::with_retry(&retry_config, || {
+            client.get(ADOBE_PROCESS_URL).headers(adobe_headers.clone()).send()
+        })?;
+        let mut status_code = status_resp.status();
+        let mut body = status_resp.text()?;
+
+        if has_live_session && !logged_in_this_cycle && looks_unauthenticated(status_code, &body) {
+            tracing::warn!(
+                "reused session cookie was rejected by the portal; forcing a fresh login and retrying"
+            );
+            perform_login(
+                &client,
+                &retry_config,
+                &username,
+                &password,
+                Path::new(&jar_path),
+                &mut stored_cookies,
+            )?;
+            // The client's own cookie jar now holds the fresh session from
+            // the login above; the manually-forwarded stale cookie would
+            // only confuse things.
+            adobe_headers.remove(reqwest::header::COOKIE);
+
+            let retry_resp = retry::with_retry(&retry_config, || {
+                client.get(ADOBE_PROCESS_URL).headers(adobe_headers.clone()).send()
+            })?;
+            status_code = retry_resp.status();
+            body = retry_resp.text()?;
+        }
+
+        tracing::debug!(status = %status_code, %body, "status response body");
+        println!("{body}");
+        return Ok(date_expire);
+    }
+
+    if cli.dry_run {
+        // Print the payload that would have been submitted instead of
+        // sending it.
+        println!("Dry run: would POST to {ADOBE_URL} with payload:");
+        for (key, value) in adobe_data {
+            println!("  {key} = {value}");
+        }
+        return Ok(date_expire);
+    }
+
+    let adobe_resp = retry::with_retry(&retry_config, || {
+        client.post(ADOBE_URL).headers(adobe_headers.clone()).form(&adobe_data).send()
+    })?;
+    let mut status_code = adobe_resp.status();
+    let mut body = adobe_resp.text()?;
+
+    if has_live_session && !logged_in_this_cycle && looks_unauthenticated(status_code, &body) {
+        tracing::warn!(
+            "reused session cookie was rejected by the portal; forcing a fresh login and retrying"
+        );
+        perform_login(
+            &client,
+            &retry_config,
+            &username,
+            &password,
+            Path::new(&jar_path),
+            &mut stored_cookies,
+        )?;
+        adobe_headers.remove(reqwest::header::COOKIE);
+
+        let retry_resp = retry::with_retry(&retry_config, || {
+            client.post(ADOBE_URL).headers(adobe_headers.clone()).form(&adobe_data).send()
+        })?;
+        status_code = retry_resp.status();
+        body = retry_resp.text()?;
+    }
 
     // Print the response body for visibility (original behavior)
-    println!("{}", adobe_resp.text()?);
+    tracing::debug!(status = %status_code, %body, "Adobe reservation response body");
+    println!("{body}");
 
+    Ok(date_expire)
+}
+
+/// `--check`: report the portal's certificate validity (like an ops health
+/// check) without logging in or touching the reservation.
+fn run_check() -> Result<(), Box<dyn std::error::Error>> {
+    let status = tls::check_expiry(LOGIN_DOMAIN)?;
+    println!(
+        "{LOGIN_DOMAIN}: certificate valid until {} ({} day(s) remaining)",
+        status.not_after, status.days_remaining
+    );
+    if status.is_near_expiry(tls_min_days()) {
+        eprintln!("warning: certificate is within the expiry warning threshold");
+    }
     Ok(())
 }
 
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Default to `info` so the renewal/login progress that used to go to
+    // stdout via println! is still visible when RUST_LOG isn't set.
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    tracing_subscriber::fmt().with_env_filter(env_filter).init();
+
+    let cli = Cli::parse();
+
+    if cli.check {
+        run_check()
+    } else if cli.daemon {
+        // The daemon loop always treats a failed cycle as a failure (so it
+        // backs off and retries rather than sleeping as if renewed);
+        // --ignore-errors only changes the one-shot exit behavior below.
+        daemon::run(&cli)
+    } else {
+        match run_once(&cli, false) {
+            Ok(_) => Ok(()),
+            Err(e) if cli.ignore_errors && e.is::<reqwest::Error>() => {
+                tracing::error!(error = %e, "run failed; suppressing error due to --ignore-errors");
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn one_month_lease(year: i32, month: u32) -> String {
+        let today = chrono::NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        compute_date_expire(today, LeaseSpec::Months(1))
+    }
+
     #[test]
     fn test_make_date_expire_january() {
-        // If month is January, should return December of previous year
-        assert_eq!(make_date_expire(2024, 1), "2023-12-01");
+        // January should roll forward into February of the same year, not
+        // back into December of the *previous* year (the original bug).
+        assert_eq!(one_month_lease(2024, 1), "2024-02-01");
     }
 
     #[test]
     fn test_make_date_expire_other_months() {
         // For other months, should return next month same year
-        assert_eq!(make_date_expire(2024, 6), "2024-07-01");
-        assert_eq!(make_date_expire(2024, 11), "2024-12-01");
-        assert_eq!(make_date_expire(2024, 2), "2024-03-01");
+        assert_eq!(one_month_lease(2024, 6), "2024-07-01");
+        assert_eq!(one_month_lease(2024, 11), "2024-12-01");
+        assert_eq!(one_month_lease(2024, 2), "2024-03-01");
+    }
+
+    #[test]
+    fn test_make_date_expire_december_rolls_into_next_year() {
+        assert_eq!(one_month_lease(2024, 12), "2025-01-01");
+    }
+
+    #[test]
+    fn test_compute_date_expire_multi_month_lease() {
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 10, 15).unwrap();
+        assert_eq!(compute_date_expire(today, LeaseSpec::Months(3)), "2025-01-01");
+    }
+
+    #[test]
+    fn test_compute_date_expire_explicit_override() {
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 10, 15).unwrap();
+        let expire = chrono::NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        assert_eq!(
+            compute_date_expire(today, LeaseSpec::ExplicitDate(expire)),
+            "2025-06-01"
+        );
     }
 }
@@ -0,0 +1,123 @@
+//! Retry-with-backoff wrapper around the HTTP calls.
+//!
+//! `reqwest::blocking::RequestBuilder::send()` only returns `Err` for
+//! transport-level problems (connection refused, timeout, DNS failure, ...);
+//! an HTTP 4xx/5xx still comes back as `Ok(Response)`. That means retrying
+//! every `Err` from `send()` is exactly "retry transient network errors,
+//! don't retry on 4xx", with no extra status-code bookkeeping needed.
+
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Retry policy: how many attempts to make and how long to wait between them.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Build a config from `HTTP_RETRY_MAX_ATTEMPTS` / `HTTP_RETRY_BASE_DELAY_MS`,
+    /// falling back to sane defaults.
+    pub fn from_env() -> Self {
+        let defaults = RetryConfig::default();
+        RetryConfig {
+            max_attempts: std::env::var("HTTP_RETRY_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_attempts),
+            base_delay: std::env::var("HTTP_RETRY_BASE_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.base_delay),
+        }
+    }
+}
+
+/// A pseudo-random fraction in `[0, 1)`, derived from the clock so jitter
+/// doesn't need an extra RNG dependency.
+fn jitter_fraction() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    (nanos % 1000) as f64 / 1000.0
+}
+
+/// Exponential backoff with up to 20% jitter: `base * 2^(attempt - 1)`.
+fn backoff_delay(base: Duration, attempt: u32) -> Duration {
+    let exp_millis = base.as_millis() as u64 * 2u64.saturating_pow(attempt.saturating_sub(1));
+    let jitter_millis = (exp_millis as f64 * 0.2 * jitter_fraction()) as u64;
+    Duration::from_millis(exp_millis + jitter_millis)
+}
+
+/// Run `attempt` up to `config.max_attempts` times, sleeping with
+/// exponential backoff and jitter between failures. Returns the last error
+/// if every attempt fails.
+pub fn with_retry<F, T>(config: &RetryConfig, mut attempt: F) -> Result<T, reqwest::Error>
+where
+    F: FnMut() -> Result<T, reqwest::Error>,
+{
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempts < config.max_attempts => {
+                let delay = backoff_delay(config.base_delay, attempts);
+                tracing::warn!(attempt = attempts, ?delay, error = %e, "HTTP request failed, retrying");
+                thread::sleep(delay);
+            }
+            Err(e) => {
+                tracing::error!(attempts, error = %e, "HTTP request failed after all retries");
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn default_config_matches_documented_values() {
+        let config = RetryConfig::default();
+        assert_eq!(config.max_attempts, 3);
+        assert_eq!(config.base_delay, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn backoff_grows_exponentially() {
+        let base = Duration::from_millis(100);
+        assert!(backoff_delay(base, 1) >= base);
+        assert!(backoff_delay(base, 2) >= Duration::from_millis(200));
+        assert!(backoff_delay(base, 3) >= Duration::from_millis(400));
+    }
+
+    #[test]
+    fn succeeds_without_retry_when_first_attempt_succeeds() {
+        let calls = Cell::new(0);
+        let config = RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let result: Result<u32, reqwest::Error> = with_retry(&config, || {
+            calls.set(calls.get() + 1);
+            Ok(42)
+        });
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,101 @@
+//! TLS trust configuration: certificate pinning plus an expiry pre-check, to
+//! replace blindly disabling certificate verification.
+//!
+//! When the `TLS_PIN_BUNDLE_PATH` env var names a PEM bundle, the client pins
+//! the portal's certificate (leaf or issuing CA) from it instead of trusting
+//! the system root store; without it, the system store is used. The old `danger_accept_invalid_certs`
+//! behavior is still available, but only behind an explicit `--insecure`
+//! flag, since this tool submits a plaintext-in-the-clear-to-TLS password.
+
+use openssl::asn1::Asn1Time;
+use openssl::ssl::{SslConnector, SslMethod};
+use reqwest::blocking::{Client, ClientBuilder};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Default threshold, in days, under which [`check_expiry`] is considered a
+/// warning-worthy (or refusal-worthy) state.
+pub const DEFAULT_MIN_DAYS_REMAINING: i64 = 14;
+
+/// Result of inspecting the portal's presented leaf certificate.
+#[derive(Debug, Clone)]
+pub struct CertStatus {
+    pub not_after: String,
+    pub days_remaining: i64,
+}
+
+impl CertStatus {
+    /// Whether this certificate is within `min_days` of expiring.
+    pub fn is_near_expiry(&self, min_days: i64) -> bool {
+        self.days_remaining < min_days
+    }
+}
+
+/// Build the HTTP client used for both the login and Adobe requests.
+///
+/// - `insecure`: replicate the old `danger_accept_invalid_certs(true)` behavior; only
+///   meant to be reachable behind an explicit `--insecure` CLI flag.
+/// - `pin_bundle_path`: when `insecure` is false and this is `Some`, the PEM bundle at
+///   that path is added as a pinned root certificate instead of trusting the system
+///   store.
+pub fn build_client(
+    insecure: bool,
+    pin_bundle_path: Option<&str>,
+    timeout: Duration,
+) -> Result<Client, Box<dyn std::error::Error>> {
+    let mut builder: ClientBuilder = Client::builder().cookie_store(true).timeout(timeout);
+
+    if insecure {
+        builder = builder.danger_accept_invalid_certs(true);
+    } else if let Some(path) = pin_bundle_path {
+        let pem = std::fs::read(path)
+            .map_err(|e| format!("failed to read TLS pin bundle at {path:?}: {e}"))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .map_err(|e| format!("invalid PEM in TLS pin bundle {path:?}: {e}"))?;
+        // Disable the built-in root store too, otherwise this only *adds* a
+        // trusted anchor and any of the system's hundreds of public CAs can
+        // still validate an impostor certificate.
+        builder = builder
+            .add_root_certificate(cert)
+            .tls_built_in_root_certs(false);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Connect to `host:443` and report how many days remain before the
+/// presented leaf certificate's `notAfter` boundary.
+pub fn check_expiry(host: &str) -> Result<CertStatus, Box<dyn std::error::Error>> {
+    let connector = SslConnector::builder(SslMethod::tls())?.build();
+    let stream = TcpStream::connect((host, 443))?;
+    let ssl_stream = connector.connect(host, stream)?;
+
+    let cert = ssl_stream
+        .ssl()
+        .peer_certificate()
+        .ok_or("server did not present a certificate")?;
+
+    let not_after = cert.not_after().to_string();
+    let now = Asn1Time::days_from_now(0)?;
+    let days_remaining = now.diff(cert.not_after())?.days as i64;
+
+    Ok(CertStatus {
+        not_after,
+        days_remaining,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn near_expiry_threshold() {
+        let status = CertStatus {
+            not_after: "Jan  1 00:00:00 2000 GMT".to_string(),
+            days_remaining: 5,
+        };
+        assert!(status.is_near_expiry(DEFAULT_MIN_DAYS_REMAINING));
+        assert!(!status.is_near_expiry(1));
+    }
+}